@@ -1,9 +1,15 @@
 //! jhash can be used as the hash algorithm in a `libstd` HashMap.
 //! It implements the necessary traits to be a drop-in replacement for the default hasher.
 //!
-//! Keep in mind: `jhash` is a 32-bit hash, whereas the `libstd` HashMap implementation expects
-//! 64-bit hashes. The 32-bit hash value is simply padded to a 64-bit value and might cause more
-//! collisions than with a real 64-bit hash.
+//! `JHasher` hashes into a full 64-bit value via `jhash64` (`jhash64_keyed` when keyed),
+//! buffering writes so that chunked writes equal one write. `JHashState` seeds it with a public,
+//! reproducible `u32`; `RandomJHashState` (`std` only) seeds it with a secret 128-bit key, drawn
+//! afresh for each new `RandomJHashState`, for HashDoS resistance.
+//!
+//! Without the `std` feature there's no allocator to buffer an arbitrarily long key in, so
+//! `JHasher` only buffers up to one 12-byte jhash block exactly; writes beyond that fall back to
+//! chaining `jhash64` calls, each seeded with the low 32 bits of the previous result, which no
+//! longer guarantees chunked writes equal one write.
 //!
 //! # Basic example
 //!
@@ -24,39 +30,122 @@
 //! assert_eq!(Some(&456), hashmap.get("def"));
 //! ```
 
-use std::default::Default;
-use std::hash::Hasher;
-use rand::{self,Rng};
-use std::hash::BuildHasher;
-use {jhash};
+use core::hash::{BuildHasher, Hasher};
+#[cfg(feature = "std")]
+use rand::{self, Rng};
+use jhash64;
+#[cfg(feature = "std")]
+use jhash64_keyed;
+
+#[cfg(feature = "std")]
+enum Seed {
+    Plain(u32),
+    Keyed(u32, u32, u32, u32),
+}
 
+#[cfg(feature = "std")]
 pub struct JHasher {
-    state: u32
+    seed: Seed,
+    buffer: Vec<u8>,
 }
 
+#[cfg(feature = "std")]
 impl JHasher {
     pub fn new() -> JHasher {
         JHasher::with_seed(0)
     }
 
     pub fn with_seed(seed: u32) -> JHasher {
-        JHasher { state: seed }
+        JHasher { seed: Seed::Plain(seed), buffer: Vec::new() }
+    }
+
+    /// Seeds with a secret 128-bit key instead of a single public `u32`; see `RandomJHashState`.
+    pub fn with_key(k0: u32, k1: u32, k2: u32, k3: u32) -> JHasher {
+        JHasher { seed: Seed::Keyed(k0, k1, k2, k3), buffer: Vec::new() }
     }
 }
 
+#[cfg(feature = "std")]
 impl Default for JHasher {
     fn default() -> JHasher {
         JHasher::new()
     }
 }
 
+#[cfg(feature = "std")]
 impl Hasher for JHasher {
     fn finish(&self) -> u64 {
-        self.state as u64
+        match self.seed {
+            Seed::Plain(seed) => jhash64(&self.buffer, seed),
+            Seed::Keyed(k0, k1, k2, k3) => jhash64_keyed(&self.buffer, k0, k1, k2, k3),
+        }
     }
 
     fn write(&mut self, buf: &[u8]) {
-        self.state = jhash(buf, self.state);
+        self.buffer.extend_from_slice(buf);
+    }
+}
+
+#[cfg(not(feature = "std"))]
+const BLOCK_LEN: usize = 12;
+
+// No allocator here, so only one jhash block (12 bytes) is buffered exactly; a write that would
+// overflow it instead folds the block (and everything after) into a chained `jhash64`, which no
+// longer matches hashing the same bytes in a single `write` but still avalanches into a full 64
+// bits, unlike chaining the 32-bit `jhash`.
+#[cfg(not(feature = "std"))]
+enum Buffer {
+    Block([u8; BLOCK_LEN], usize),
+    Overflowed(u64),
+}
+
+#[cfg(not(feature = "std"))]
+pub struct JHasher {
+    seed: u32,
+    buffer: Buffer,
+}
+
+#[cfg(not(feature = "std"))]
+impl JHasher {
+    pub fn new() -> JHasher {
+        JHasher::with_seed(0)
+    }
+
+    pub fn with_seed(seed: u32) -> JHasher {
+        JHasher { seed, buffer: Buffer::Block([0; BLOCK_LEN], 0) }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Default for JHasher {
+    fn default() -> JHasher {
+        JHasher::new()
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl Hasher for JHasher {
+    fn finish(&self) -> u64 {
+        match self.buffer {
+            Buffer::Block(ref block, len) => jhash64(&block[..len], self.seed),
+            Buffer::Overflowed(state) => state,
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) {
+        if let Buffer::Block(ref mut block, ref mut len) = self.buffer {
+            if *len + buf.len() <= BLOCK_LEN {
+                block[*len..*len + buf.len()].copy_from_slice(buf);
+                *len += buf.len();
+                return;
+            }
+        }
+
+        let state = match self.buffer {
+            Buffer::Block(ref block, len) => jhash64(&block[..len], self.seed),
+            Buffer::Overflowed(state) => state,
+        };
+        self.buffer = Buffer::Overflowed(jhash64(buf, state as u32));
     }
 }
 
@@ -82,29 +171,37 @@ impl BuildHasher for JHashState {
     }
 }
 
-pub struct RandomJHashState(u32);
+/// Like `JHashState`, but seeded from a secret 128-bit key that `new` draws from `thread_rng`
+/// for each instance, for HashDoS resistance. `Clone` is cheap, so the same key can be reused across several maps.
+#[cfg(feature = "std")]
+#[derive(Clone)]
+pub struct RandomJHashState(u32, u32, u32, u32);
 
+#[cfg(feature = "std")]
 impl RandomJHashState {
     pub fn new() -> RandomJHashState {
-        RandomJHashState(rand::thread_rng().gen())
+        let mut rng = rand::thread_rng();
+        RandomJHashState(rng.gen(), rng.gen(), rng.gen(), rng.gen())
     }
 }
 
+#[cfg(feature = "std")]
 impl Default for RandomJHashState {
     fn default() -> RandomJHashState {
         RandomJHashState::new()
     }
 }
 
+#[cfg(feature = "std")]
 impl BuildHasher for RandomJHashState {
     type Hasher = JHasher;
 
     fn build_hasher(&self) -> JHasher {
-        JHasher::with_seed(self.0)
+        JHasher::with_key(self.0, self.1, self.2, self.3)
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod test {
     use super::{JHasher, JHashState, RandomJHashState};
     use std::collections::HashMap;
@@ -151,4 +248,38 @@ mod test {
         map.insert(42, "the answer");
         assert_eq!(map.get(&42), Some(&"the answer"));
     }
+
+    #[test]
+    fn random_state_is_consistent_within_one_instance() {
+        use std::hash::{BuildHasher, Hasher};
+
+        let s = RandomJHashState::new();
+        let mut a = s.build_hasher();
+        let mut b = s.build_hasher();
+        a.write(b"foobar");
+        b.write(b"foobar");
+        assert_eq!(a.finish(), b.finish());
+    }
+
+    #[test]
+    fn chunked_writes_match_single_write() {
+        use std::hash::Hasher;
+
+        let key = b"the quick brown fox jumps over";
+
+        let mut one_shot = JHasher::new();
+        one_shot.write(key);
+        let expected = one_shot.finish();
+
+        let mut two_chunks = JHasher::new();
+        two_chunks.write(&key[..10]);
+        two_chunks.write(&key[10..]);
+        assert_eq!(expected, two_chunks.finish());
+
+        let mut per_byte = JHasher::new();
+        for b in key {
+            per_byte.write(&[*b]);
+        }
+        assert_eq!(expected, per_byte.finish());
+    }
 }
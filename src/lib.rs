@@ -24,6 +24,14 @@ extern crate quickcheck;
 
 #[cfg(feature = "std")]
 extern crate core;
+#[cfg(feature = "std")]
+extern crate rand;
+
+pub mod hasher;
+#[cfg(feature = "std")]
+pub mod map;
+#[cfg(feature = "std")]
+pub mod quality;
 
 #[cfg(all(test, feature = "std"))]
 use std::os::raw::c_void;
@@ -46,6 +54,32 @@ pub fn native_jhash(key: &[u8], initval: u32) -> u32 {
     }
 }
 
+#[cfg(all(test, feature = "std"))]
+extern {
+    #[link_name = "jhash_3words"]
+    fn __jhash_3words_native(a: u32, b: u32, c: u32, initval: u32) -> u32;
+}
+
+#[cfg(all(test, feature = "std"))]
+pub fn native_jhash_3words(a: u32, b: u32, c: u32, initval: u32) -> u32 {
+    unsafe { __jhash_3words_native(a, b, c, initval) }
+}
+
+#[cfg(all(test, feature = "std"))]
+extern {
+    #[link_name = "jhash2"]
+    fn __jhash2_native(k: *const u32, length: u32, initval: u32) -> u32;
+}
+
+#[cfg(all(test, feature = "std"))]
+pub fn native_jhash2(k: &[u32], initval: u32) -> u32 {
+    unsafe {
+        let length = k.len() as u32;
+
+        __jhash2_native(k.as_ptr(), length, initval)
+    }
+}
+
 // code from
 // https://github.com/BurntSushi/byteorder/blob/7f90e282f629f2864d7fc14640ea710dab6ddc95/src/lib.rs#L314-L327
 fn get_u32(val: &[u8]) -> u32 {
@@ -96,6 +130,45 @@ fn __jhash_mix(oa: &mut u32, ob: &mut u32, oc: &mut u32) {
     *oc = c;
 }
 
+fn jhash_bc(key: &[u8], initval: u32) -> (u32, u32) {
+    let length = key.len();
+
+    let mut a = JHASH_INITVAL.wrapping_add(length as u32).wrapping_add(initval);
+    let mut b = a;
+    let mut c = a;
+
+    let mut offset = 0;
+    while (length-offset) > 12 {
+        a = a.wrapping_add(get_u32(&key[offset as usize..]));
+        b = b.wrapping_add(get_u32(&key[offset as usize+4..]));
+        c = c.wrapping_add(get_u32(&key[offset as usize+8..]));
+
+        __jhash_mix(&mut a, &mut b, &mut c);
+        offset += 12;
+    }
+
+    let remaining = length-offset;
+    if remaining == 12 { c = c.wrapping_add((key[offset+11] as u32) << 24); }
+    if remaining >= 11 { c = c.wrapping_add((key[offset+10] as u32) << 16); }
+    if remaining >= 10 { c = c.wrapping_add((key[offset+9] as u32) << 8); }
+    if remaining >= 9  { c = c.wrapping_add(key[offset+8] as u32); }
+
+    if remaining >= 8 { b = b.wrapping_add((key[offset+7] as u32) << 24); }
+    if remaining >= 7 { b = b.wrapping_add((key[offset+6] as u32) << 16); }
+    if remaining >= 6 { b = b.wrapping_add((key[offset+5] as u32) << 8); }
+    if remaining >= 5 { b = b.wrapping_add(key[offset+4] as u32); }
+
+    if remaining >= 4 { a = a.wrapping_add((key[offset+3] as u32) << 24); }
+    if remaining >= 3 { a = a.wrapping_add((key[offset+2] as u32) << 16); }
+    if remaining >= 2 { a = a.wrapping_add((key[offset+1] as u32) << 8); }
+    if remaining >= 1 {
+        a = a.wrapping_add(key[offset] as u32);
+        __jhash_final(&mut a, &mut b, &mut c);
+    }
+
+    (b, c)
+}
+
 /// Hashes an arbitrary sequence of bytes.
 ///
 /// `initval` is a previous hash or an arbitrary value
@@ -103,11 +176,29 @@ fn __jhash_mix(oa: &mut u32, ob: &mut u32, oc: &mut u32) {
 ///
 /// Returns the hash value of the key. The result depends on endianness.
 pub fn jhash(key: &[u8], initval: u32) -> u32 {
+    jhash_bc(key, initval).1
+}
+
+/// Hashes an arbitrary sequence of bytes into a full 64-bit value.
+///
+/// `initval` is a previous hash or an arbitrary value. Combines `jhash`'s independent `b` and
+/// `c` output words, giving a real 64-bit spread instead of a zero-padded 32-bit one.
+pub fn jhash64(key: &[u8], initval: u32) -> u64 {
+    let (b, c) = jhash_bc(key, initval);
+    ((c as u64) << 32) | (b as u64)
+}
+
+/// Hashes an arbitrary sequence of bytes under a secret 64-bit key `k0`/`k1`.
+///
+/// Diffuses the key across all three lanes before and after absorbing the input, so collisions
+/// can't be crafted without knowing the key the way they can against plain `jhash`.
+pub fn jhash_keyed(key: &[u8], k0: u32, k1: u32) -> u32 {
     let length = key.len();
 
-    let mut a = JHASH_INITVAL.wrapping_add(length as u32).wrapping_add(initval);
-    let mut b = a;
-    let mut c = a;
+    let mut a = JHASH_INITVAL.wrapping_add(k0);
+    let mut b = JHASH_INITVAL.wrapping_add(k1);
+    let mut c = JHASH_INITVAL.wrapping_add(length as u32);
+    __jhash_mix(&mut a, &mut b, &mut c);
 
     let mut offset = 0;
     while (length-offset) > 12 {
@@ -133,14 +224,89 @@ pub fn jhash(key: &[u8], initval: u32) -> u32 {
     if remaining >= 4 { a = a.wrapping_add((key[offset+3] as u32) << 24); }
     if remaining >= 3 { a = a.wrapping_add((key[offset+2] as u32) << 16); }
     if remaining >= 2 { a = a.wrapping_add((key[offset+1] as u32) << 8); }
+    if remaining >= 1 { a = a.wrapping_add(key[offset] as u32); }
+
+    a = a.wrapping_add(k0);
+    b = b.wrapping_add(k1);
+    __jhash_mix(&mut a, &mut b, &mut c);
+    __jhash_final(&mut a, &mut b, &mut c);
+
+    c
+}
+
+/// Hashes an arbitrary sequence of bytes into a full 64-bit value under a secret 128-bit key.
+///
+/// Runs `jhash_keyed` twice, once per 64-bit half of the key, and combines the two results the
+/// way `jhash64` combines `jhash`'s `b` and `c`.
+pub fn jhash64_keyed(key: &[u8], k0: u32, k1: u32, k2: u32, k3: u32) -> u64 {
+    let lo = jhash_keyed(key, k0, k1);
+    let hi = jhash_keyed(key, k2, k3);
+    ((hi as u64) << 32) | (lo as u64)
+}
+
+/// Hashes an array of `u32` words.
+///
+/// The word-oriented counterpart to [`jhash`](fn.jhash.html), mirroring the Linux kernel's
+/// `jhash2`. Useful for hashing tuples of integers (IP addresses, port pairs, ...) without
+/// reinterpreting them as bytes or depending on endianness.
+pub fn jhash2(k: &[u32], initval: u32) -> u32 {
+    let length = k.len();
+
+    let mut a = JHASH_INITVAL.wrapping_add((length as u32) << 2).wrapping_add(initval);
+    let mut b = a;
+    let mut c = a;
+
+    let mut offset = 0;
+    while length - offset > 3 {
+        a = a.wrapping_add(k[offset]);
+        b = b.wrapping_add(k[offset + 1]);
+        c = c.wrapping_add(k[offset + 2]);
+
+        __jhash_mix(&mut a, &mut b, &mut c);
+        offset += 3;
+    }
+
+    let remaining = length - offset;
+    if remaining >= 3 { c = c.wrapping_add(k[offset + 2]); }
+    if remaining >= 2 { b = b.wrapping_add(k[offset + 1]); }
     if remaining >= 1 {
-        a = a.wrapping_add(key[offset] as u32);
+        a = a.wrapping_add(k[offset]);
         __jhash_final(&mut a, &mut b, &mut c);
     }
 
     c
 }
 
+/// Hashes three words, matching the Linux kernel's `__jhash_nwords`: `initval` is added to each
+/// of `a`, `b`, `c` before finalizing.
+///
+/// This is the building block `jhash_1word`/`jhash_2words`/`jhash_3words` wrap, each passing
+/// `initval + JHASH_INITVAL + (n << 2)` for its word count `n`; most callers want one of those
+/// instead.
+pub fn jhash_nwords(a: u32, b: u32, c: u32, initval: u32) -> u32 {
+    let mut a = a.wrapping_add(initval);
+    let mut b = b.wrapping_add(initval);
+    let mut c = c.wrapping_add(initval);
+
+    __jhash_final(&mut a, &mut b, &mut c);
+    c
+}
+
+/// Hashes three words, e.g. a tuple of integers such as an address and two ports.
+pub fn jhash_3words(a: u32, b: u32, c: u32, initval: u32) -> u32 {
+    jhash_nwords(a, b, c, initval.wrapping_add(JHASH_INITVAL).wrapping_add(3 << 2))
+}
+
+/// Hashes two words. Same as `jhash_nwords(a, b, 0, initval + JHASH_INITVAL + (2 << 2))`.
+pub fn jhash_2words(a: u32, b: u32, initval: u32) -> u32 {
+    jhash_nwords(a, b, 0, initval.wrapping_add(JHASH_INITVAL).wrapping_add(2 << 2))
+}
+
+/// Hashes a single word. Same as `jhash_nwords(a, 0, 0, initval + JHASH_INITVAL + (1 << 2))`.
+pub fn jhash_1word(a: u32, initval: u32) -> u32 {
+    jhash_nwords(a, 0, 0, initval.wrapping_add(JHASH_INITVAL).wrapping_add(1 << 2))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -155,6 +321,54 @@ mod tests {
         assert_eq!(0xaeb72b0c, jhash(b"foobar", 0));
     }
 
+    #[test]
+    fn jhash64_upper_word_matches_jhash() {
+        // jhash64 puts `c`, the word jhash returns, in its upper 32 bits, so
+        // they must equal the plain 32-bit jhash result for the same input.
+        let key = b"foobar";
+        let h64 = jhash64(key, 0);
+        assert_eq!((h64 >> 32) as u32, jhash(key, 0));
+    }
+
+    #[test]
+    fn jhash_keyed_depends_on_the_key() {
+        let key = b"foobar";
+        assert_eq!(jhash_keyed(key, 1, 2), jhash_keyed(key, 1, 2));
+        assert_ne!(jhash_keyed(key, 1, 2), jhash_keyed(key, 1, 3));
+        assert_ne!(jhash_keyed(key, 1, 2), jhash_keyed(key, 2, 2));
+        assert_ne!(jhash_keyed(key, 0, 0), jhash(key, 0));
+    }
+
+    #[test]
+    fn jhash64_keyed_uses_all_four_words() {
+        let key = b"foobar";
+        let base = jhash64_keyed(key, 1, 2, 3, 4);
+        assert_eq!(base, jhash64_keyed(key, 1, 2, 3, 4));
+        assert_ne!(base, jhash64_keyed(key, 1, 2, 3, 5));
+        assert_ne!(base, jhash64_keyed(key, 1, 2, 5, 4));
+        assert_ne!(base, jhash64_keyed(key, 1, 5, 3, 4));
+        assert_ne!(base, jhash64_keyed(key, 5, 2, 3, 4));
+    }
+
+    #[test]
+    fn jhash2_is_deterministic() {
+        assert_eq!(jhash2(&[1, 2, 3, 4], 0), jhash2(&[1, 2, 3, 4], 0));
+        assert_ne!(jhash2(&[1, 2, 3, 4], 0), jhash2(&[1, 2, 3, 5], 0));
+    }
+
+    #[test]
+    fn jhash_words_fold_their_length_into_the_seed() {
+        assert_eq!(jhash_1word(42, 0), jhash_nwords(42, 0, 0, JHASH_INITVAL.wrapping_add(1 << 2)));
+        assert_eq!(jhash_2words(42, 7, 0), jhash_nwords(42, 7, 0, JHASH_INITVAL.wrapping_add(2 << 2)));
+    }
+
+    #[test]
+    fn jhash_words_are_sensitive_to_each_word() {
+        assert_ne!(jhash_1word(1, 0), jhash_1word(2, 0));
+        assert_ne!(jhash_2words(1, 2, 0), jhash_2words(1, 3, 0));
+        assert_ne!(jhash_3words(1, 2, 3, 0), jhash_3words(1, 2, 4, 0));
+    }
+
     #[cfg(all(test, feature = "std"))]
     #[test]
     fn same() {
@@ -166,6 +380,30 @@ mod tests {
     fn same_high_initval() {
         assert_eq!(jhash(b"foob", 2411127588), native_jhash(b"foob", 2411127588));
     }
+
+    #[cfg(all(test, feature = "std"))]
+    #[test]
+    fn jhash_3words_same_as_native() {
+        assert_eq!(jhash_3words(1, 2, 3, 0), native_jhash_3words(1, 2, 3, 0));
+    }
+
+    #[cfg(all(test, feature = "std"))]
+    #[test]
+    fn jhash_3words_same_as_native_high_initval() {
+        assert_eq!(jhash_3words(1, 2, 3, 2411127588), native_jhash_3words(1, 2, 3, 2411127588));
+    }
+
+    #[cfg(all(test, feature = "std"))]
+    #[test]
+    fn jhash2_same_as_native() {
+        assert_eq!(jhash2(&[1, 2, 3, 4], 0), native_jhash2(&[1, 2, 3, 4], 0));
+    }
+
+    #[cfg(all(test, feature = "std"))]
+    #[test]
+    fn jhash2_same_as_native_high_initval() {
+        assert_eq!(jhash2(&[1, 2, 3, 4], 2411127588), native_jhash2(&[1, 2, 3, 4], 2411127588));
+    }
 }
 
 #[cfg(all(test, feature = "std"))]
@@ -179,4 +417,16 @@ mod quickcheck_test {
             rust == native
         }
     }
+
+    quickcheck! {
+        fn jhash_3words_check(a: u32, b: u32, c: u32, initval: u32) -> bool {
+            jhash_3words(a, b, c, initval) == native_jhash_3words(a, b, c, initval)
+        }
+    }
+
+    quickcheck! {
+        fn jhash2_check(data: Vec<u32>, initval: u32) -> bool {
+            jhash2(&data, initval) == native_jhash2(&data, initval)
+        }
+    }
 }
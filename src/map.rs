@@ -0,0 +1,78 @@
+//! Type aliases for `HashMap`/`HashSet` built on jhash, so callers don't have to spell out the
+//! `BuildHasher` generic at every call site, the way ahash's `AHashMap`/`AHashSet` do.
+//!
+//! Both default to `RandomJHashState`. Use `HashMap::with_hasher(JHashState::new())` instead if
+//! you need a reproducible hash across runs.
+
+use std::collections::{HashMap, HashSet};
+use hasher::RandomJHashState;
+
+pub type JHashMap<K, V> = HashMap<K, V, RandomJHashState>;
+pub type JHashSet<T> = HashSet<T, RandomJHashState>;
+
+/// `new`/`with_capacity` constructors for `JHashMap`, since a type alias can't carry its own
+/// inherent `impl`.
+pub trait JHashMapExt {
+    fn new() -> Self;
+    fn with_capacity(capacity: usize) -> Self;
+}
+
+impl<K, V> JHashMapExt for JHashMap<K, V> {
+    fn new() -> Self {
+        HashMap::with_hasher(RandomJHashState::new())
+    }
+
+    fn with_capacity(capacity: usize) -> Self {
+        HashMap::with_capacity_and_hasher(capacity, RandomJHashState::new())
+    }
+}
+
+/// `new`/`with_capacity` constructors for `JHashSet`, since a type alias can't carry its own
+/// inherent `impl`.
+pub trait JHashSetExt {
+    fn new() -> Self;
+    fn with_capacity(capacity: usize) -> Self;
+}
+
+impl<T> JHashSetExt for JHashSet<T> {
+    fn new() -> Self {
+        HashSet::with_hasher(RandomJHashState::new())
+    }
+
+    fn with_capacity(capacity: usize) -> Self {
+        HashSet::with_capacity_and_hasher(capacity, RandomJHashState::new())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{JHashMap, JHashMapExt, JHashSet, JHashSetExt};
+
+    #[test]
+    fn map_new_and_insert() {
+        let mut map = JHashMap::new();
+        map.insert("abc", 123);
+        assert_eq!(Some(&123), map.get("abc"));
+    }
+
+    #[test]
+    fn map_with_capacity() {
+        let mut map: JHashMap<_, _> = JHashMapExt::with_capacity(16);
+        map.insert(1, "one");
+        assert_eq!(Some(&"one"), map.get(&1));
+    }
+
+    #[test]
+    fn set_new_and_insert() {
+        let mut set = JHashSet::new();
+        set.insert("abc");
+        assert!(set.contains("abc"));
+    }
+
+    #[test]
+    fn set_with_capacity() {
+        let mut set: JHashSet<_> = JHashSetExt::with_capacity(16);
+        set.insert(42);
+        assert!(set.contains(&42));
+    }
+}
@@ -0,0 +1,198 @@
+//! Empirical diffusion diagnostics for the hash functions in this crate.
+//!
+//! Unlike the golden-value and FFI-equality tests in `lib.rs`, which only catch regressions in
+//! already-known outputs, these functions measure how well a hash actually mixes its input, so a
+//! regression in `__jhash_mix`/`__jhash_final` that still passes the golden vectors can still be
+//! caught. Downstream crates can run these against `jhash`/`jhash64` and gate their own CI on the
+//! reported statistics instead of trusting fixed vectors alone.
+
+use rand::{self, Rng};
+use std::collections::HashSet;
+
+/// Result of [`avalanche_test`](fn.avalanche_test.html): for each output bit, the fraction of
+/// single-input-bit flips that also flipped it. A well-mixed hash keeps every entry close to
+/// `0.5` (flipping one input bit should flip each output bit about half the time).
+pub struct AvalancheReport {
+    pub samples: usize,
+    pub flip_frequency: Vec<f64>,
+    pub worst_deviation: f64,
+}
+
+/// Flips every bit of `samples` random `key_len`-byte keys, one at a time, and records how
+/// often each of the hash's `bits` output bits changes.
+///
+/// `hash` should return its result in the low `bits` bits of the `u64`, e.g.
+/// `|k| jhash(k, 0) as u64` for a 32-bit hash or `|k| jhash64(k, 0)` for a 64-bit one.
+pub fn avalanche_test<F>(hash: F, bits: usize, key_len: usize, samples: usize) -> AvalancheReport
+    where F: Fn(&[u8]) -> u64
+{
+    let mut rng = rand::thread_rng();
+    let mut flips = vec![0u64; bits];
+    let mut trials = 0u64;
+
+    for _ in 0..samples {
+        let key: Vec<u8> = (0..key_len).map(|_| rng.gen()).collect();
+        let base = hash(&key);
+
+        for bit in 0..(key_len * 8) {
+            let mut flipped = key.clone();
+            flipped[bit / 8] ^= 1 << (bit % 8);
+            let diff = base ^ hash(&flipped);
+
+            for (out_bit, count) in flips.iter_mut().enumerate().take(bits) {
+                if (diff >> out_bit) & 1 == 1 {
+                    *count += 1;
+                }
+            }
+            trials += 1;
+        }
+    }
+
+    let flip_frequency: Vec<f64> = flips.iter().map(|&c| c as f64 / trials as f64).collect();
+    let worst_deviation = flip_frequency.iter()
+        .map(|f| (f - 0.5).abs())
+        .fold(0.0, f64::max);
+
+    AvalancheReport { samples, flip_frequency, worst_deviation }
+}
+
+/// Result of [`bit_independence_test`](fn.bit_independence_test.html): how far the worst pair
+/// of output bits is from flipping independently of one another.
+pub struct BitIndependenceReport {
+    pub samples: usize,
+    pub worst_correlation: f64,
+}
+
+/// Like [`avalanche_test`](fn.avalanche_test.html), but checks pairs of output bits instead of
+/// single ones: two output bits are independent if the odds of both flipping together match the
+/// product of their individual flip odds. The worst (largest) deviation across all pairs is
+/// reported.
+pub fn bit_independence_test<F>(hash: F, bits: usize, key_len: usize, samples: usize) -> BitIndependenceReport
+    where F: Fn(&[u8]) -> u64
+{
+    let mut rng = rand::thread_rng();
+    let mut single_flip = vec![0u64; bits];
+    let mut co_flip = vec![vec![0u64; bits]; bits];
+    let mut trials = 0u64;
+
+    for _ in 0..samples {
+        let key: Vec<u8> = (0..key_len).map(|_| rng.gen()).collect();
+        let base = hash(&key);
+
+        for bit in 0..(key_len * 8) {
+            let mut flipped = key.clone();
+            flipped[bit / 8] ^= 1 << (bit % 8);
+            let diff = base ^ hash(&flipped);
+
+            for (i, (single, co)) in single_flip.iter_mut().zip(co_flip.iter_mut()).enumerate().take(bits) {
+                if (diff >> i) & 1 != 1 { continue; }
+                *single += 1;
+                for (j, count) in co.iter_mut().enumerate().take(bits) {
+                    if (diff >> j) & 1 == 1 {
+                        *count += 1;
+                    }
+                }
+            }
+            trials += 1;
+        }
+    }
+
+    let mut worst_correlation = 0.0f64;
+    for i in 0..bits {
+        for j in 0..bits {
+            if i == j { continue; }
+            let p_i = single_flip[i] as f64 / trials as f64;
+            let p_j = single_flip[j] as f64 / trials as f64;
+            let p_ij = co_flip[i][j] as f64 / trials as f64;
+            worst_correlation = worst_correlation.max((p_ij - p_i * p_j).abs());
+        }
+    }
+
+    BitIndependenceReport { samples, worst_correlation }
+}
+
+/// Result of [`collision_test`](fn.collision_test.html): how many collisions actually showed up
+/// among the sampled keys, against the birthday-bound expectation for a hash of `bits` bits.
+pub struct CollisionReport {
+    pub sample_count: usize,
+    pub observed_collisions: usize,
+    pub expected_collisions: f64,
+}
+
+/// Hashes every key in `keys` and counts collisions, i.e. keys that land on a hash value already
+/// produced by an earlier key. `expected_collisions` is the birthday-bound estimate
+/// `n * (n - 1) / (2 * 2^bits)` for `n` samples over a `bits`-bit output space, for comparison.
+pub fn collision_test<F, I>(hash: F, bits: usize, keys: I) -> CollisionReport
+    where F: Fn(&[u8]) -> u64, I: IntoIterator<Item = Vec<u8>>
+{
+    let mut seen = HashSet::new();
+    let mut observed_collisions = 0usize;
+    let mut sample_count = 0usize;
+
+    for key in keys {
+        if !seen.insert(hash(&key)) {
+            observed_collisions += 1;
+        }
+        sample_count += 1;
+    }
+
+    let n = sample_count as f64;
+    let space = 2f64.powi(bits as i32);
+    let expected_collisions = n * (n - 1.0) / (2.0 * space);
+
+    CollisionReport { sample_count, observed_collisions, expected_collisions }
+}
+
+/// `0, 1, 2, ...` as little-endian 4-byte keys, the simplest realistic worst case for a hash
+/// used as a `HashMap`'s bucket index (e.g. consecutive integer IDs).
+pub fn sequential_keys(count: usize) -> Vec<Vec<u8>> {
+    (0..count as u32).map(|i| i.to_le_bytes().to_vec()).collect()
+}
+
+/// `count` keys spread across the full 32-bit range via a large odd stride, instead of being
+/// consecutive, to catch a hash that only mixes well for small or monotonically increasing
+/// inputs.
+pub fn sparse_keys(count: usize) -> Vec<Vec<u8>> {
+    const STRIDE: u32 = 0x9e37_79b9;
+    (0..count as u32).map(|i| i.wrapping_mul(STRIDE).to_le_bytes().to_vec()).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use {jhash, jhash64};
+
+    #[test]
+    fn avalanche_is_close_to_half() {
+        let report = avalanche_test(|k| jhash(k, 0) as u64, 32, 8, 64);
+        assert!(report.worst_deviation < 0.35, "worst deviation: {}", report.worst_deviation);
+    }
+
+    #[test]
+    fn avalanche_64bit_is_close_to_half() {
+        let report = avalanche_test(|k| jhash64(k, 0), 64, 8, 64);
+        assert!(report.worst_deviation < 0.35, "worst deviation: {}", report.worst_deviation);
+    }
+
+    #[test]
+    fn bit_independence_is_low() {
+        let report = bit_independence_test(|k| jhash(k, 0) as u64, 32, 8, 64);
+        assert!(report.worst_correlation < 0.5, "worst correlation: {}", report.worst_correlation);
+    }
+
+    #[test]
+    fn collisions_are_near_expected() {
+        let keys = sequential_keys(2000);
+        let report = collision_test(|k| jhash(k, 0) as u64, 32, keys);
+        assert_eq!(2000, report.sample_count);
+        // A 32-bit hash over 2000 sequential keys should rarely collide at all.
+        assert!((report.observed_collisions as f64) < report.expected_collisions + 5.0);
+    }
+
+    #[test]
+    fn sparse_keys_are_distinct() {
+        let keys = sparse_keys(16);
+        let unique: HashSet<_> = keys.iter().collect();
+        assert_eq!(16, unique.len());
+    }
+}